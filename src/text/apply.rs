@@ -0,0 +1,367 @@
+//! Reconstructing a sequence from a diff.
+use std::fmt;
+
+use super::DiffableStr;
+use crate::{DiffOp, DiffTag};
+
+/// An error returned when applying a diff or an ed script fails.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ApplyError {
+    /// An ed command referenced an empty or descending line range.
+    InvalidRange {
+        /// The first line of the offending range.
+        first: usize,
+        /// The last line of the offending range.
+        last: usize,
+    },
+    /// An ed command or diff op referenced a position outside of the
+    /// document bounds.
+    OutOfBounds {
+        /// The offending line number (for ed commands) or range endpoint
+        /// (for diff ops).
+        line: usize,
+        /// The number of lines in the document at the time of the command.
+        len: usize,
+    },
+    /// A hunk was missing its terminating `.` line.
+    UnterminatedHunk,
+    /// A line could not be parsed as an ed command.
+    InvalidCommand(String),
+    /// A verified script was missing its leading `hash` digest header.
+    MissingDigestHeader,
+    /// A recomputed digest didn't match the one recorded in the script.
+    DigestMismatch {
+        /// Which document the mismatching digest was for.
+        stage: DigestStage,
+        /// The digest recorded in the script (hex-encoded).
+        expected: String,
+        /// The digest computed locally (hex-encoded).
+        actual: String,
+    },
+}
+
+/// Identifies which document a [`ApplyError::DigestMismatch`] refers to.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DigestStage {
+    /// The pre-image, i.e. the document the script is applied to.
+    Old,
+    /// The post-image, i.e. the document the script should produce.
+    New,
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyError::InvalidRange { first, last } => {
+                write!(f, "invalid ed range {},{}", first, last)
+            }
+            ApplyError::OutOfBounds { line, len } => {
+                write!(f, "line {} is out of bounds for document of {} lines", line, len)
+            }
+            ApplyError::UnterminatedHunk => write!(f, "ed hunk is missing a terminating `.` line"),
+            ApplyError::InvalidCommand(line) => write!(f, "invalid ed command: {:?}", line),
+            ApplyError::MissingDigestHeader => write!(f, "script is missing its `hash` digest header"),
+            ApplyError::DigestMismatch {
+                stage,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} digest mismatch: expected {}, got {}",
+                match stage {
+                    DigestStage::Old => "old",
+                    DigestStage::New => "new",
+                },
+                expected,
+                actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+/// Applies a sequence of [`DiffOp`]s, reconstructing the new sequence from
+/// `old` and `new`.
+///
+/// Content for [`DiffTag::Insert`] and [`DiffTag::Replace`] ops is taken
+/// from `new`, while unchanged ([`DiffTag::Equal`]) content is taken from
+/// `old`.  This is mostly useful to replay ops captured against one copy of
+/// a sequence (for instance parsed from an ed script) against another,
+/// logically equivalent copy.
+///
+/// `old` and `new` must be long enough to satisfy every range the ops
+/// reference; since `ops` may have been captured against different slices
+/// than the ones passed in here, this is returned as an [`ApplyError`]
+/// rather than panicking on an out-of-bounds index.
+pub fn apply_diff_ops<'t, T: DiffableStr + ?Sized>(
+    ops: &[DiffOp],
+    old: &[&'t T],
+    new: &[&'t T],
+) -> Result<Vec<&'t T>, ApplyError> {
+    let mut rv = Vec::new();
+    for op in ops {
+        match op.tag() {
+            DiffTag::Equal => {
+                let range = op.old_range();
+                check_slice_bounds(&range, old.len())?;
+                rv.extend_from_slice(&old[range]);
+            }
+            DiffTag::Delete => {}
+            DiffTag::Insert | DiffTag::Replace => {
+                let range = op.new_range();
+                check_slice_bounds(&range, new.len())?;
+                rv.extend_from_slice(&new[range]);
+            }
+        }
+    }
+    Ok(rv)
+}
+
+fn check_slice_bounds(range: &std::ops::Range<usize>, len: usize) -> Result<(), ApplyError> {
+    if range.end > len {
+        return Err(ApplyError::OutOfBounds {
+            line: range.end,
+            len,
+        });
+    }
+    Ok(())
+}
+
+/// Marker line, mirroring unified diff's `\ No newline at end of file`,
+/// that [`super::EdDiff`] emits right after a content line which didn't
+/// actually end in a newline in the source document. Without it the ed
+/// script can't distinguish "this line really ended in `\n`" from "a `\n`
+/// was added only to delimit the content line from the next one".
+pub(crate) const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
+enum EdCommandKind {
+    Delete,
+    Change,
+    Append,
+}
+
+struct EdCommand {
+    kind: EdCommandKind,
+    first: usize,
+    last: usize,
+    content: Vec<String>,
+}
+
+fn parse_ed_range(range: &str, line: &str) -> Result<(usize, usize), ApplyError> {
+    let (first, last) = match range.split_once(',') {
+        Some((a, b)) => (
+            a.parse().map_err(|_| ApplyError::InvalidCommand(line.to_string()))?,
+            b.parse().map_err(|_| ApplyError::InvalidCommand(line.to_string()))?,
+        ),
+        None => {
+            let n = range
+                .parse()
+                .map_err(|_| ApplyError::InvalidCommand(line.to_string()))?;
+            (n, n)
+        }
+    };
+    if first > last {
+        return Err(ApplyError::InvalidRange { first, last });
+    }
+    Ok((first, last))
+}
+
+fn parse_ed_script(script: &str) -> Result<Vec<EdCommand>, ApplyError> {
+    let mut lines = script.lines();
+    let mut commands = Vec::new();
+
+    while let Some(line) = lines.next() {
+        let (range, kind) = if let Some(range) = line.strip_suffix('d') {
+            (range, EdCommandKind::Delete)
+        } else if let Some(range) = line.strip_suffix('c') {
+            (range, EdCommandKind::Change)
+        } else if let Some(range) = line.strip_suffix('a') {
+            (range, EdCommandKind::Append)
+        } else {
+            return Err(ApplyError::InvalidCommand(line.to_string()));
+        };
+        let (first, last) = parse_ed_range(range, line)?;
+
+        let mut content = Vec::new();
+        if !matches!(kind, EdCommandKind::Delete) {
+            loop {
+                match lines.next() {
+                    Some(".") => break,
+                    Some(NO_NEWLINE_MARKER) => {
+                        // The previous content line was only `\n`-terminated
+                        // to delimit it in the script; it didn't actually
+                        // end in a newline in the source document.
+                        if let Some(last) = content.last_mut() {
+                            last.pop();
+                        }
+                    }
+                    // `lines()` strips the terminator; put it back so content
+                    // lines keep the same shape as the lines in `old` (each
+                    // including its own trailing `\n`, unless marked
+                    // otherwise by a following `NO_NEWLINE_MARKER`).
+                    Some(content_line) => content.push(format!("{}\n", content_line)),
+                    None => return Err(ApplyError::UnterminatedHunk),
+                }
+            }
+        }
+
+        commands.push(EdCommand {
+            kind,
+            first,
+            last,
+            content,
+        });
+    }
+
+    Ok(commands)
+}
+
+fn check_bounds(first: usize, last: usize, len: usize) -> Result<(), ApplyError> {
+    if first == 0 || last > len {
+        return Err(ApplyError::OutOfBounds {
+            line: if first == 0 { first } else { last },
+            len,
+        });
+    }
+    Ok(())
+}
+
+/// Applies an ed script produced by [`TextDiff::ed_diff`](crate::TextDiff::ed_diff)
+/// to `old`, returning the reconstructed document.
+///
+/// Commands are buffered and then applied in descending line order so that
+/// line numbers referenced by earlier commands stay valid regardless of the
+/// order they appear in the script.
+///
+/// Returns an [`ApplyError`] if the script contains a malformed or
+/// out-of-range command, or a hunk that is missing its terminating `.`
+/// line.
+pub fn apply_ed_script<T: DiffableStr + ?Sized>(
+    script: &str,
+    old: &[&T],
+) -> Result<Vec<String>, ApplyError> {
+    let mut commands = parse_ed_script(script)?;
+    commands.sort_by(|a, b| b.first.cmp(&a.first));
+
+    let mut lines: Vec<String> = old.iter().map(|x| x.to_string_lossy().into_owned()).collect();
+
+    for cmd in commands {
+        match cmd.kind {
+            EdCommandKind::Delete => {
+                check_bounds(cmd.first, cmd.last, lines.len())?;
+                lines.drain(cmd.first - 1..cmd.last);
+            }
+            EdCommandKind::Change => {
+                check_bounds(cmd.first, cmd.last, lines.len())?;
+                lines.splice(cmd.first - 1..cmd.last, cmd.content);
+            }
+            EdCommandKind::Append => {
+                if cmd.first > lines.len() {
+                    return Err(ApplyError::OutOfBounds {
+                        line: cmd.first,
+                        len: lines.len(),
+                    });
+                }
+                let tail = lines.split_off(cmd.first);
+                lines.extend(cmd.content);
+                lines.extend(tail);
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Hex-encodes `bytes`, lowercase, no separators.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    let mut rv = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(rv, "{:02x}", byte).ok();
+    }
+    rv
+}
+
+/// Concatenates the raw bytes of a slice of diffable lines.
+///
+/// Uses [`DiffableStr::as_bytes`] rather than [`DiffableStr::to_string_lossy`]
+/// so that, under the `bytes` feature, digests are computed over the actual
+/// document bytes instead of a lossy UTF-8 approximation of them.
+pub(crate) fn concat_bytes<T: DiffableStr + ?Sized>(lines: &[&T]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for line in lines {
+        buf.extend_from_slice(line.as_bytes());
+    }
+    buf
+}
+
+fn concat_owned_bytes(lines: &[String]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for line in lines {
+        buf.extend_from_slice(line.as_bytes());
+    }
+    buf
+}
+
+fn split_digest_header(script: &str) -> Result<(String, String, &str), ApplyError> {
+    let mut parts = script.splitn(2, '\n');
+    let header = parts.next().unwrap_or_default();
+    let rest = parts.next().unwrap_or_default();
+    let mut digests = header
+        .strip_prefix("hash ")
+        .ok_or(ApplyError::MissingDigestHeader)?
+        .split(' ');
+    let old_digest = digests
+        .next()
+        .ok_or(ApplyError::MissingDigestHeader)?
+        .to_string();
+    let new_digest = digests
+        .next()
+        .ok_or(ApplyError::MissingDigestHeader)?
+        .to_string();
+    Ok((old_digest, new_digest, rest))
+}
+
+/// Like [`apply_ed_script`], but first verifies a leading `hash` header
+/// (as emitted by [`EdDiff::digest`](super::EdDiff::digest)) against
+/// digests computed with `hasher`, and verifies the result the same way
+/// once the script has been applied.
+///
+/// This mirrors how the Tor consensus-diff format guards patches against
+/// being applied to the wrong base document, or silently producing the
+/// wrong result. `hasher` is deliberately algorithm-agnostic so callers can
+/// plug in SHA-256, SHA-3, BLAKE3, or anything else.
+pub fn apply_ed_script_verified<T, H>(
+    script: &str,
+    old: &[&T],
+    hasher: H,
+) -> Result<Vec<String>, ApplyError>
+where
+    T: DiffableStr + ?Sized,
+    H: Fn(&[u8]) -> Vec<u8>,
+{
+    let (expected_old, expected_new, rest) = split_digest_header(script)?;
+
+    let actual_old = to_hex(&hasher(&concat_bytes(old)));
+    if actual_old != expected_old {
+        return Err(ApplyError::DigestMismatch {
+            stage: DigestStage::Old,
+            expected: expected_old,
+            actual: actual_old,
+        });
+    }
+
+    let new_lines = apply_ed_script(rest, old)?;
+
+    let actual_new = to_hex(&hasher(&concat_owned_bytes(&new_lines)));
+    if actual_new != expected_new {
+        return Err(ApplyError::DigestMismatch {
+            stage: DigestStage::New,
+            expected: expected_new,
+            actual: actual_new,
+        });
+    }
+
+    Ok(new_lines)
+}
@@ -0,0 +1,122 @@
+//! Ed script diff printing.
+use std::fmt;
+use std::io::{self, Write};
+
+use super::apply::{concat_bytes, to_hex, NO_NEWLINE_MARKER};
+use super::{DiffableStr, TextDiff};
+use crate::{DiffOp, DiffTag};
+
+/// Ed script diff formatter.
+///
+/// This can be created with [`TextDiff::ed_diff`].
+///
+/// Requires the `text` feature.
+pub struct EdDiff<'diff, 'old, 'new, 'bufs, T: DiffableStr + ?Sized> {
+    diff: &'diff TextDiff<'old, 'new, 'bufs, T>,
+    digest_header: Option<(String, String)>,
+}
+
+impl<'diff, 'old, 'new, 'bufs, T: DiffableStr + ?Sized> EdDiff<'diff, 'old, 'new, 'bufs, T> {
+    /// Creates a formatter from a text diff object.
+    pub fn from_text_diff(diff: &'diff TextDiff<'old, 'new, 'bufs, T>) -> Self {
+        EdDiff {
+            diff,
+            digest_header: None,
+        }
+    }
+
+    /// Records a caller-supplied digest of the old and new document in a
+    /// leading `hash` header line.
+    ///
+    /// `hasher` is run over the concatenated bytes of
+    /// [`TextDiff::old_slices`] and, separately, [`TextDiff::new_slices`];
+    /// the hex-encoded results are emitted as `hash <old> <new>` ahead of
+    /// the ed commands. This is what [`apply_ed_script_verified`](super::apply_ed_script_verified)
+    /// checks before and after applying the script, so kept algorithm-agnostic
+    /// rather than tied to a specific hash crate.
+    pub fn digest<H>(&mut self, hasher: H) -> &mut Self
+    where
+        H: Fn(&[u8]) -> Vec<u8>,
+    {
+        let old_digest = to_hex(&hasher(&concat_bytes(self.diff.old_slices())));
+        let new_digest = to_hex(&hasher(&concat_bytes(self.diff.new_slices())));
+        self.digest_header = Some((old_digest, new_digest));
+        self
+    }
+
+    /// Write the ed script to the output stream.
+    pub fn to_writer<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "{}", self)
+    }
+
+    fn write_content_line<W: fmt::Write>(&self, w: &mut W, line: &T) -> fmt::Result {
+        let s = line.to_string_lossy();
+        // Only a real, newline-terminated line can legitimately lack a `\n`
+        // here; for word/char diffs `newline_terminated()` is false and a
+        // missing `\n` is just how the token looks, not a fact about the
+        // document's last line.
+        let no_trailing_newline = self.diff.newline_terminated() && !s.ends_with('\n');
+        w.write_str(&s)?;
+        if !self.diff.newline_terminated() || !s.ends_with('\n') {
+            w.write_char('\n')?;
+        }
+        if no_trailing_newline {
+            writeln!(w, "{}", NO_NEWLINE_MARKER)?;
+        }
+        Ok(())
+    }
+
+    fn write_hunk<W: fmt::Write>(&self, w: &mut W, op: &DiffOp) -> fmt::Result {
+        let old_range = op.old_range();
+        let new_range = op.new_range();
+        match op.tag() {
+            DiffTag::Equal => Ok(()),
+            DiffTag::Delete => {
+                let first = old_range.start + 1;
+                let last = old_range.end;
+                if first == last {
+                    writeln!(w, "{}d", first)
+                } else {
+                    writeln!(w, "{},{}d", first, last)
+                }
+            }
+            DiffTag::Insert => {
+                writeln!(w, "{}a", old_range.start)?;
+                for line in &self.diff.new_slices()[new_range] {
+                    self.write_content_line(w, line)?;
+                }
+                writeln!(w, ".")
+            }
+            DiffTag::Replace => {
+                let first = old_range.start + 1;
+                let last = old_range.end;
+                if first == last {
+                    writeln!(w, "{}c", first)?;
+                } else {
+                    writeln!(w, "{},{}c", first, last)?;
+                }
+                for line in &self.diff.new_slices()[new_range] {
+                    self.write_content_line(w, line)?;
+                }
+                writeln!(w, ".")
+            }
+        }
+    }
+}
+
+impl<'diff, 'old, 'new, 'bufs, T: DiffableStr + ?Sized> fmt::Display
+    for EdDiff<'diff, 'old, 'new, 'bufs, T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((old_digest, new_digest)) = &self.digest_header {
+            writeln!(f, "hash {} {}", old_digest, new_digest)?;
+        }
+        // ed scripts must be emitted in descending order of old-line position so
+        // that line numbers referenced by earlier commands stay valid as the
+        // script is applied top-down against the original document.
+        for op in self.diff.ops().iter().rev() {
+            self.write_hunk(f, op)?;
+        }
+        Ok(())
+    }
+}
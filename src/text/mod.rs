@@ -4,11 +4,17 @@ use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 
 mod abstraction;
+mod apply;
+mod ed;
 #[cfg(feature = "inline")]
 mod inline;
 mod utils;
 
 pub use self::abstraction::{DiffableStr, DiffableStrRef};
+pub use self::apply::{
+    apply_diff_ops, apply_ed_script, apply_ed_script_verified, ApplyError, DigestStage,
+};
+pub use self::ed::EdDiff;
 #[cfg(feature = "inline")]
 pub use self::inline::InlineChange;
 
@@ -339,6 +345,28 @@ impl<'old, 'new, 'bufs, T: DiffableStr + ?Sized + 'old + 'new> TextDiff<'old, 'n
         UnifiedDiff::from_text_diff(self)
     }
 
+    /// Utility to return an ed-style script diff formatter.
+    pub fn ed_diff<'diff>(&'diff self) -> EdDiff<'diff, 'old, 'new, 'bufs, T> {
+        EdDiff::from_text_diff(self)
+    }
+
+    /// Applies this diff's ops to `old_lines`, reconstructing the new
+    /// sequence.
+    ///
+    /// This is a shortcut for calling [`apply_diff_ops`] with this diff's
+    /// [`TextDiff::ops`] and [`TextDiff::new_slices`], which supplies the
+    /// content for insertions and replacements.
+    ///
+    /// `old_lines` need not be the same slice this diff was computed from,
+    /// but it must cover every range the ops keep unchanged from the old
+    /// side; an [`ApplyError`] is returned otherwise.
+    pub fn apply_to<'t>(&self, old_lines: &[&'t T]) -> Result<Vec<&'t T>, ApplyError>
+    where
+        'new: 't,
+    {
+        apply_diff_ops(self.ops(), old_lines, self.new_slices())
+    }
+
     /// Iterates over the changes the op expands to with inline emphasis.
     ///
     /// This is very similar to [`TextDiff::iter_changes`] but it performs a second
@@ -451,6 +479,120 @@ fn test_unified_diff() {
         .to_string());
 }
 
+#[test]
+fn test_ed_diff() {
+    let diff = TextDiff::from_lines(
+        "Hello World\nsome stuff here\nsome more stuff here\n",
+        "Hello World\nsome amazing stuff here\nsome more stuff here\n",
+    );
+    assert_eq!(diff.newline_terminated(), true);
+    insta::assert_snapshot!(&diff.ed_diff().to_string());
+}
+
+#[test]
+fn test_ed_diff_multi_hunk_roundtrips() {
+    // Two separate, non-adjacent hunks: an insert near the top and a
+    // delete near the bottom, so the descending-order emission actually
+    // has more than one command to reorder.
+    let a = "one\ntwo\nthree\nfour\nfive\n";
+    let b = "one\nintroduced\ntwo\nthree\nfour\n";
+    let diff = TextDiff::from_lines(a, b);
+    let script = diff.ed_diff().to_string();
+    let rebuilt = apply_ed_script(&script, diff.old_slices()).unwrap();
+    assert_eq!(rebuilt.concat(), b);
+}
+
+#[test]
+fn test_apply_to_roundtrips() {
+    let a = "Hello World\nsome stuff here\nsome more stuff here\n";
+    let b = "Hello World\nsome amazing stuff here\nsome more stuff here\n";
+    let diff = TextDiff::from_lines(a, b);
+    let new_lines = diff.apply_to(diff.old_slices()).unwrap();
+    let rebuilt: String = new_lines.iter().map(|x| x.to_string_lossy()).collect();
+    assert_eq!(rebuilt, b);
+}
+
+#[test]
+fn test_apply_ed_script_roundtrips() {
+    let a = "Hello World\nsome stuff here\nsome more stuff here\n";
+    let b = "Hello World\nsome amazing stuff here\nsome more stuff here\n";
+    let diff = TextDiff::from_lines(a, b);
+    let script = diff.ed_diff().to_string();
+    let rebuilt = apply_ed_script(&script, diff.old_slices()).unwrap();
+    assert_eq!(rebuilt.concat(), b);
+}
+
+#[test]
+fn test_apply_ed_script_roundtrips_without_trailing_newline() {
+    // The last line of `b` has no trailing newline, which previously got
+    // reattached by the ed-script round trip because the script had no
+    // way to tell a real line ending apart from the `\n` used only to
+    // delimit a content line from the next one.
+    let a = "a\nb\n";
+    let b = "a\nc";
+    let diff = TextDiff::from_lines(a, b);
+    let script = diff.ed_diff().to_string();
+    let rebuilt = apply_ed_script(&script, diff.old_slices()).unwrap();
+    assert_eq!(rebuilt.concat(), b);
+}
+
+#[test]
+fn test_apply_diff_ops_out_of_bounds() {
+    let a = "Hello World\nsome stuff here\nsome more stuff here\n";
+    let b = "Hello World\nsome amazing stuff here\nsome more stuff here\n";
+    let diff = TextDiff::from_lines(a, b);
+    let err = apply_diff_ops(diff.ops(), &diff.old_slices()[..1], diff.new_slices()).unwrap_err();
+    assert!(matches!(err, ApplyError::OutOfBounds { .. }));
+}
+
+#[test]
+fn test_apply_ed_script_errors() {
+    assert_eq!(
+        apply_ed_script::<str>("bogus", &[]).unwrap_err(),
+        ApplyError::InvalidCommand("bogus".to_string())
+    );
+    assert_eq!(
+        apply_ed_script::<str>("5d", &["a\n", "b\n"]).unwrap_err(),
+        ApplyError::OutOfBounds { line: 5, len: 2 }
+    );
+    assert_eq!(
+        apply_ed_script::<str>("1a\nfoo\n", &["a\n"]).unwrap_err(),
+        ApplyError::UnterminatedHunk
+    );
+}
+
+fn fake_hash(bytes: &[u8]) -> Vec<u8> {
+    vec![bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b))]
+}
+
+#[test]
+fn test_apply_ed_script_verified_roundtrips() {
+    let a = "Hello World\nsome stuff here\nsome more stuff here\n";
+    let b = "Hello World\nsome amazing stuff here\nsome more stuff here\n";
+    let diff = TextDiff::from_lines(a, b);
+    let script = diff.ed_diff().digest(fake_hash).to_string();
+    let rebuilt = apply_ed_script_verified(&script, diff.old_slices(), fake_hash).unwrap();
+    assert_eq!(rebuilt.concat(), b);
+}
+
+#[test]
+fn test_apply_ed_script_verified_detects_wrong_base() {
+    let a = "Hello World\nsome stuff here\nsome more stuff here\n";
+    let b = "Hello World\nsome amazing stuff here\nsome more stuff here\n";
+    let diff = TextDiff::from_lines(a, b);
+    let script = diff.ed_diff().digest(fake_hash).to_string();
+
+    let wrong_base = TextDiff::from_lines("completely different\n", b).old_slices().to_vec();
+    let err = apply_ed_script_verified(&script, &wrong_base, fake_hash).unwrap_err();
+    assert!(matches!(
+        err,
+        ApplyError::DigestMismatch {
+            stage: DigestStage::Old,
+            ..
+        }
+    ));
+}
+
 #[test]
 fn test_line_ops() {
     let a = "Hello World\nsome stuff here\nsome more stuff here\n";